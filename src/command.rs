@@ -1,32 +1,147 @@
-use crate::OneWire;
-use core::convert::Infallible;
-use embedded_hal::{
-    delay::DelayUs,
-    digital::{ErrorType, InputPin, OutputPin},
-};
+use crate::{OneWireDriver, Result};
+use embedded_hal::delay::DelayNs;
 
-/// Commander
-pub trait Commander {
-    fn run<C: Command>(&mut self, command: C) -> C::Output;
+use crate::commands::Pin;
+
+/// Bus-level primitives a 1-Wire master must provide.
+///
+/// [`OneWireDriver`] implements this by bit-banging a GPIO pin, but the same
+/// primitives are also what a hardware bridge such as the I2C-attached
+/// DS2482 or the USB-attached DS2490 exposes. Every [`Command`] is written
+/// against `impl Bus` rather than the concrete GPIO driver, so the same ROM
+/// and memory commands run unchanged against a bridge implementation - only
+/// this trait needs a new impl.
+pub trait Bus {
+    /// Issues a reset pulse and returns whether any device asserted a
+    /// presence pulse in response.
+    fn reset(&mut self) -> Result<bool>;
+
+    /// Reads a single bit.
+    fn read_bit(&mut self) -> Result<bool>;
+
+    /// Writes a single bit.
+    fn write_bit(&mut self, bit: bool) -> Result<()>;
+
+    /// Reads enough bits to fill `bytes`, least-significant bit first.
+    fn read_bytes(&mut self, bytes: &mut [u8]) -> Result<()>;
+
+    /// Writes `bytes`, least-significant bit first.
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<()>;
+
+    /// Writes a single byte.
+    fn write_byte(&mut self, byte: u8) -> Result<()> {
+        self.write_bytes(&[byte])
+    }
+
+    /// Reads a single byte.
+    fn read_byte(&mut self) -> Result<u8> {
+        let mut byte = [0u8; 1];
+        self.read_bytes(&mut byte)?;
+        Ok(byte[0])
+    }
+
+    /// Actively drives the bus high for `duration_micros` instead of
+    /// releasing it to the pull-up, so a parasite-powered slave has enough
+    /// current to finish an operation such as a temperature conversion.
+    /// Each implementation supplies this its own way - a bit-banged driver
+    /// drives its GPIO pin directly, while a bridge chip such as the
+    /// DS2482 has a dedicated strong-pullup control of its own.
+    fn strong_pullup(&mut self, duration_micros: u32) -> Result<()>;
+
+    /// Search-accelerator primitive: reads the id bit and its complement,
+    /// picks a direction (`preferred_direction` when both read as `0`, i.e.
+    /// there is a discrepancy between devices still coupled to the search;
+    /// otherwise the id bit itself), writes that direction back, and returns
+    /// all three in a single round
+    /// trip. Bridge chips like the DS2482 implement this as one hardware
+    /// transaction; the default falls back to separate read/read/write
+    /// calls.
+    fn triplet(&mut self, preferred_direction: bool) -> Result<(bool, bool, bool)> {
+        let id_bit = self.read_bit()?;
+        let cmp_id_bit = self.read_bit()?;
+        let direction = if id_bit != cmp_id_bit {
+            id_bit
+        } else {
+            preferred_direction
+        };
+        self.write_bit(direction)?;
+        Ok((id_bit, cmp_id_bit, direction))
+    }
+
+    /// Performs a single read slot to check whether a pending temperature
+    /// conversion has finished, without blocking.
+    ///
+    /// Unlike [`MemoryConversionWait`](crate::MemoryConversionWait), this
+    /// never waits out a timeout itself, so an event loop can call it once
+    /// per tick for each of several sensors and interleave their
+    /// conversions instead of blocking on them one at a time. Only
+    /// meaningful for externally powered devices, for the same reason
+    /// `MemoryConversionWait` is. A plain read slot is all any backend
+    /// needs for this, so the default works unchanged on a bridge chip too.
+    fn is_conversion_complete(&mut self) -> Result<bool> {
+        self.read_bit()
+    }
+
+    /// Approximate wall-clock time one [`read_bit`](Bus::read_bit) round
+    /// trip takes on this bus.
+    ///
+    /// The read-slot polling loops in
+    /// [`MemoryConversionWait`](crate::MemoryConversionWait),
+    /// [`MemoryRecall`](crate::MemoryRecall),
+    /// [`MemoryScratchpadCopy`](crate::MemoryScratchpadCopy), and
+    /// [`MemCopyScratchpad`](crate::MemCopyScratchpad) divide their timeout
+    /// by this to size their retry count, so it has to reflect the actual
+    /// backend: the default of 70 matches a bit-banged GPIO driver's own
+    /// read slot, but a bridge chip whose `read_bit` goes out over a
+    /// slower transport (e.g. I2C) should override this with its own
+    /// round-trip time, or those loops will give up long before the
+    /// device is actually done.
+    fn read_slot_micros(&self) -> u32 {
+        70
+    }
+}
+
+impl<P: Pin, D: DelayNs> Bus for OneWireDriver<P, D> {
+    fn reset(&mut self) -> Result<bool> {
+        Ok(self.reset()?)
+    }
+
+    fn read_bit(&mut self) -> Result<bool> {
+        Ok(self.read_bit()?)
+    }
+
+    fn write_bit(&mut self, bit: bool) -> Result<()> {
+        Ok(self.write_bit(bit)?)
+    }
+
+    fn read_bytes(&mut self, bytes: &mut [u8]) -> Result<()> {
+        Ok(self.read_bytes(bytes)?)
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<()> {
+        Ok(self.write_bytes(bytes)?)
+    }
+
+    fn strong_pullup(&mut self, duration_micros: u32) -> Result<()> {
+        self.strong_pullup(duration_micros)
+    }
 }
 
-impl<T: InputPin + OutputPin + ErrorType<Error = Infallible>, U: DelayUs> Commander
-    for OneWire<T, U>
-{
-    fn run<C: Command>(&mut self, command: C) -> C::Output {
+/// Commander
+pub trait Commander: Bus {
+    fn run<C: Command>(&mut self, command: C) -> C::Output
+    where
+        Self: Sized,
+    {
         command.execute(self)
     }
 }
 
+impl<T: Bus> Commander for T {}
+
 /// Command
 pub trait Command {
     type Output;
 
-    fn execute(
-        &self,
-        one_wire: &mut OneWire<
-            impl InputPin + OutputPin + ErrorType<Error = Infallible>,
-            impl DelayUs,
-        >,
-    ) -> Self::Output;
+    fn execute(&self, bus: &mut impl Bus) -> Self::Output;
 }
@@ -0,0 +1,295 @@
+use super::RomMatch;
+use crate::{command::Commander, error::Error, Bus, Command, Result, Rom};
+
+pub const COMMAND_MEM_WRITE_SCRATCHPAD: u8 = 0x0F;
+pub const COMMAND_MEM_READ_SCRATCHPAD: u8 = 0xAA;
+pub const COMMAND_MEM_COPY_SCRATCHPAD: u8 = 0x55;
+pub const COMMAND_MEM_READ_MEMORY: u8 = 0xF0;
+
+/// Number of bytes in a scratchpad/page for the addressable EEPROM family
+/// (DS2431, DS2433, and similar parts).
+pub const PAGE_SIZE: usize = 8;
+
+/// Writes `data` to the device's scratchpad at `address`.
+///
+/// This only stages the bytes in the device's volatile scratchpad; it does
+/// not touch EEPROM. Follow it with a read-back through
+/// [`MemReadScratchpad`] before authorizing [`MemCopyScratchpad`] - the
+/// device will happily copy whatever ended up in the scratchpad, garbled or
+/// not, so the datasheet requires the master to verify it first.
+#[derive(Clone, Copy, Debug)]
+pub struct MemWriteScratchpad {
+    pub rom: Rom,
+    pub address: u16,
+    pub data: [u8; PAGE_SIZE],
+}
+
+impl Command for MemWriteScratchpad {
+    type Output = Result<()>;
+
+    fn execute(&self, bus: &mut impl Bus) -> Self::Output {
+        bus.reset()?;
+        bus.run(RomMatch { rom: self.rom })?;
+        bus.write_byte(COMMAND_MEM_WRITE_SCRATCHPAD)?;
+        bus.write_byte(self.address as u8)?;
+        bus.write_byte((self.address >> 8) as u8)?;
+        bus.write_bytes(&self.data)?;
+        Ok(())
+    }
+}
+
+/// The scratchpad contents returned by [`MemReadScratchpad`], together with
+/// the target address and ending-offset status byte the device echoes back.
+///
+/// `address` and `ending_offset` are the TA1/TA2/E-S fields from the
+/// datasheet; they double as the authorization code [`MemCopyScratchpad`]
+/// needs to copy the scratchpad into EEPROM.
+#[derive(Clone, Copy, Debug)]
+pub struct MemScratchpad {
+    pub address: u16,
+    pub ending_offset: u8,
+    pub data: [u8; PAGE_SIZE],
+}
+
+/// Reads back the device's scratchpad: the TA1/TA2 target address, the E/S
+/// status byte, and the scratchpad contents themselves.
+#[derive(Clone, Copy, Debug)]
+pub struct MemReadScratchpad {
+    pub rom: Rom,
+}
+
+impl Command for MemReadScratchpad {
+    type Output = Result<MemScratchpad>;
+
+    fn execute(&self, bus: &mut impl Bus) -> Self::Output {
+        bus.reset()?;
+        bus.run(RomMatch { rom: self.rom })?;
+        bus.write_byte(COMMAND_MEM_READ_SCRATCHPAD)?;
+        let mut address = [0u8; 2];
+        bus.read_bytes(&mut address)?;
+        let mut ending_offset = [0u8; 1];
+        bus.read_bytes(&mut ending_offset)?;
+        let mut data = [0u8; PAGE_SIZE];
+        bus.read_bytes(&mut data)?;
+        Ok(MemScratchpad {
+            address: u16::from_le_bytes(address),
+            ending_offset: ending_offset[0],
+            data,
+        })
+    }
+}
+
+/// Copies the scratchpad into EEPROM, authorized with the TA1/TA2/E-S fields
+/// the device echoed back from [`MemReadScratchpad`].
+///
+/// Polls read slots for up to ~10 ms after the copy is issued, returning as
+/// soon as the device reads as `1`, or [`Error::Timeout`] if it never does.
+#[derive(Clone, Copy, Debug)]
+pub struct MemCopyScratchpad {
+    pub rom: Rom,
+    pub address: u16,
+    pub ending_offset: u8,
+}
+
+impl Command for MemCopyScratchpad {
+    type Output = Result<()>;
+
+    fn execute(&self, bus: &mut impl Bus) -> Self::Output {
+        bus.reset()?;
+        bus.run(RomMatch { rom: self.rom })?;
+        bus.write_byte(COMMAND_MEM_COPY_SCRATCHPAD)?;
+        bus.write_byte(self.address as u8)?;
+        bus.write_byte((self.address >> 8) as u8)?;
+        bus.write_byte(self.ending_offset)?;
+        // wait for the copy to finish (up to 10ms)
+        let max_retries = (10000 / bus.read_slot_micros()) + 1;
+        for _ in 0..max_retries {
+            if bus.read_bit()? {
+                return Ok(());
+            }
+        }
+        Err(Error::Timeout)
+    }
+}
+
+/// Reads directly from the EEPROM array at `address`, bypassing the
+/// scratchpad (no write/verify/copy cycle is needed for a plain read).
+#[derive(Clone, Copy, Debug)]
+pub struct MemReadMemory {
+    pub rom: Rom,
+    pub address: u16,
+}
+
+impl Command for MemReadMemory {
+    type Output = Result<[u8; PAGE_SIZE]>;
+
+    fn execute(&self, bus: &mut impl Bus) -> Self::Output {
+        bus.reset()?;
+        bus.run(RomMatch { rom: self.rom })?;
+        bus.write_byte(COMMAND_MEM_READ_MEMORY)?;
+        bus.write_byte(self.address as u8)?;
+        bus.write_byte((self.address >> 8) as u8)?;
+        let mut data = [0u8; PAGE_SIZE];
+        bus.read_bytes(&mut data)?;
+        Ok(data)
+    }
+}
+
+/// Writes one page of EEPROM, verifying the scratchpad contents before
+/// authorizing the copy.
+///
+/// This is the full write workflow the DS2431/DS2433 family datasheets
+/// describe: write the scratchpad, read it back, and only proceed to copy
+/// it into EEPROM if the echoed address and data match what was sent.
+pub fn write_page(
+    bus: &mut impl Bus,
+    rom: Rom,
+    address: u16,
+    data: [u8; PAGE_SIZE],
+) -> Result<()> {
+    bus.run(MemWriteScratchpad { rom, address, data })?;
+    let read_back = bus.run(MemReadScratchpad { rom })?;
+    if read_back.address != address || read_back.data != data {
+        return Err(Error::UnexpectedResponse);
+    }
+    bus.run(MemCopyScratchpad {
+        rom,
+        address: read_back.address,
+        ending_offset: read_back.ending_offset,
+    })
+}
+
+/// Reads one page of EEPROM at `address`.
+pub fn read_page(bus: &mut impl Bus, rom: Rom, address: u16) -> Result<[u8; PAGE_SIZE]> {
+    bus.run(MemReadMemory { rom, address })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VALID_ROM: [u8; 8] = [0x28, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x9e];
+
+    /// A fake [`Bus`] that answers `read_bytes` from a scripted byte
+    /// sequence and `read_bit` from a scripted bit sequence, otherwise
+    /// ignoring everything written to it.
+    struct FakeBus<'a> {
+        read_bytes_script: &'a [u8],
+        byte_index: usize,
+        read_bits_script: &'a [bool],
+        bit_index: usize,
+    }
+
+    impl<'a> FakeBus<'a> {
+        fn new(read_bytes_script: &'a [u8], read_bits_script: &'a [bool]) -> Self {
+            Self {
+                read_bytes_script,
+                byte_index: 0,
+                read_bits_script,
+                bit_index: 0,
+            }
+        }
+    }
+
+    impl<'a> Bus for FakeBus<'a> {
+        fn reset(&mut self) -> Result<bool> {
+            Ok(true)
+        }
+
+        fn read_bit(&mut self) -> Result<bool> {
+            let bit = self
+                .read_bits_script
+                .get(self.bit_index)
+                .copied()
+                .unwrap_or(false);
+            self.bit_index += 1;
+            Ok(bit)
+        }
+
+        fn write_bit(&mut self, _bit: bool) -> Result<()> {
+            Ok(())
+        }
+
+        fn read_bytes(&mut self, bytes: &mut [u8]) -> Result<()> {
+            for byte in bytes.iter_mut() {
+                *byte = self
+                    .read_bytes_script
+                    .get(self.byte_index)
+                    .copied()
+                    .unwrap_or(0);
+                self.byte_index += 1;
+            }
+            Ok(())
+        }
+
+        fn write_bytes(&mut self, _bytes: &[u8]) -> Result<()> {
+            Ok(())
+        }
+
+        fn strong_pullup(&mut self, _duration_micros: u32) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn copy_scratchpad_times_out_if_the_device_never_finishes() {
+        let mut bus = FakeBus::new(&[], &[]);
+        let rom: Rom = VALID_ROM.try_into().unwrap();
+        assert_eq!(
+            MemCopyScratchpad {
+                rom,
+                address: 0,
+                ending_offset: 0,
+            }
+            .execute(&mut bus),
+            Err(Error::Timeout)
+        );
+    }
+
+    fn scratchpad_read_back(address: u16, ending_offset: u8, data: [u8; PAGE_SIZE]) -> [u8; 11] {
+        let address = address.to_le_bytes();
+        [
+            address[0],
+            address[1],
+            ending_offset,
+            data[0],
+            data[1],
+            data[2],
+            data[3],
+            data[4],
+            data[5],
+            data[6],
+            data[7],
+        ]
+    }
+
+    #[test]
+    fn write_page_rejects_a_scratchpad_that_does_not_match_what_was_sent() {
+        let rom: Rom = VALID_ROM.try_into().unwrap();
+        let address = 0x0010u16;
+        let data = [1, 2, 3, 4, 5, 6, 7, 8];
+
+        // The device echoes back a different address than what was written,
+        // as if the scratchpad write got corrupted in transit.
+        let read_back = scratchpad_read_back(0x0020, 0x07, data);
+
+        let mut bus = FakeBus::new(&read_back, &[]);
+        assert_eq!(
+            write_page(&mut bus, rom, address, data),
+            Err(Error::UnexpectedResponse)
+        );
+    }
+
+    #[test]
+    fn write_page_authorizes_the_copy_when_the_scratchpad_matches() {
+        let rom: Rom = VALID_ROM.try_into().unwrap();
+        let address = 0x0010u16;
+        let data = [1, 2, 3, 4, 5, 6, 7, 8];
+
+        let read_back = scratchpad_read_back(address, 0x07, data);
+
+        // One true read slot lets MemCopyScratchpad's poll succeed right away.
+        let mut bus = FakeBus::new(&read_back, &[true]);
+        assert_eq!(write_page(&mut bus, rom, address, data), Ok(()));
+    }
+}
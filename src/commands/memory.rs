@@ -2,8 +2,8 @@ use super::{Pin, RomMatch, RomSkip};
 use crate::{
     command::Commander,
     error::{Error, Result},
-    scratchpad::Scratchpad,
-    Command, OneWireDriver, Rom,
+    scratchpad::{Resolution, Scratchpad},
+    Bus, Command, OneWireDriver, Rom,
 };
 use embedded_hal::delay::DelayNs;
 
@@ -14,37 +14,111 @@ pub const COMMAND_MEMORY_SCRATCHPAD_COPY: u8 = 0x48;
 pub const COMMAND_MEMORY_SCRATCHPAD_READ: u8 = 0xBE;
 pub const COMMAND_MEMORY_SCRATCHPAD_WRITE: u8 = 0x4E;
 
-const READ_SLOT_DURATION_MICROS: u16 = 70;
+/// Duration of the conversion window for each resolution setting, per the
+/// DS18B20 datasheet (93.75/187.5/375/750 ms for 9/10/11/12-bit).
+fn conversion_micros(resolution: Resolution) -> u32 {
+    match resolution {
+        Resolution::Bits9 => 93_750,
+        Resolution::Bits10 => 187_500,
+        Resolution::Bits11 => 375_000,
+        Resolution::Bits12 => 750_000,
+    }
+}
 
 /// Initiates temperature conversion.
 ///
 /// You should wait for the measurement to finish before reading the
 /// measurement. The amount of time you need to wait depends on the current
-/// resolution configuration
+/// resolution configuration.
+///
+/// Parasite-powered devices draw the current for the conversion from the
+/// data line itself, so the ~5K pull-up resistor alone cannot supply enough
+/// current for the conversion to complete. Set `strong_pullup` to actively
+/// drive the bus high for the conversion window instead, and set
+/// `resolution` to whatever the device is currently configured for so the
+/// right window length is used.
 #[derive(Clone, Copy, Debug, Default)]
 pub struct MemoryConvert {
     pub rom: Option<Rom>,
+    pub resolution: Resolution,
+    pub strong_pullup: bool,
 }
 
 impl Command for MemoryConvert {
     type Output = Result<()>;
 
-    fn execute(&self, driver: &mut OneWireDriver<impl Pin, impl DelayNs>) -> Self::Output {
-        driver.reset()?;
+    fn execute(&self, bus: &mut impl Bus) -> Self::Output {
+        bus.reset()?;
         match self.rom {
-            Some(rom) => driver.run(RomMatch { rom })?,
-            None => driver.run(RomSkip)?,
+            Some(rom) => bus.run(RomMatch { rom })?,
+            None => bus.run(RomSkip)?,
+        }
+        bus.write_byte(COMMAND_MEMORY_CONVERT)?;
+        if self.strong_pullup {
+            bus.strong_pullup(conversion_micros(self.resolution))?;
         }
-        driver.write_byte(COMMAND_MEMORY_CONVERT)?;
         Ok(())
     }
 }
 
-/// Signals the mode of DS18B20 power supply to the master.
-#[derive(Clone, Copy, Debug)]
-pub enum MemoryPowerSupplyRead {
-    /// Signals the mode of DS18B20 power supply to the master.
-    Read,
+/// Reads whether the selected device(s) are parasite-powered.
+///
+/// Issues `0xB4` after a Skip/Match ROM and reads a single bit. The DS18B20
+/// pulls the line low for this bit if it is parasite-powered; if every
+/// device on the bus is externally powered none of them pull it low, so the
+/// bus reads as `1`.
+///
+/// If `rom` is `None` - broadcasts to all devices simultaneously, in which
+/// case a `false` result means at least one attached device is
+/// parasite-powered.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MemoryPowerSupplyRead {
+    pub rom: Option<Rom>,
+}
+
+impl Command for MemoryPowerSupplyRead {
+    type Output = Result<bool>;
+
+    fn execute(&self, bus: &mut impl Bus) -> Self::Output {
+        bus.reset()?;
+        match self.rom {
+            Some(rom) => bus.run(RomMatch { rom })?,
+            None => bus.run(RomSkip)?,
+        }
+        bus.write_byte(COMMAND_MEMORY_POWER_SUPPLY_READ)?;
+        bus.read_bit()
+    }
+}
+
+/// Blocks until an externally-powered device finishes its temperature
+/// conversion, or the resolution-appropriate upper bound elapses.
+///
+/// The DS18B20 holds the bus low while a conversion is in progress and
+/// releases it to `1` once the result is ready, so polling read slots lets
+/// the master return as soon as the conversion actually completes instead
+/// of always sleeping the worst-case window. Run this right after
+/// [`MemoryConvert`] with no reset in between. This only works for
+/// externally powered devices - a parasite-powered device cannot pull the
+/// line low while busy since it is drawing its conversion current from the
+/// line; use `MemoryConvert::strong_pullup` and sleep the full window for
+/// those instead.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MemoryConversionWait {
+    pub resolution: Resolution,
+}
+
+impl Command for MemoryConversionWait {
+    type Output = Result<()>;
+
+    fn execute(&self, bus: &mut impl Bus) -> Self::Output {
+        let max_retries = (conversion_micros(self.resolution) / bus.read_slot_micros()) + 1;
+        for _ in 0..max_retries {
+            if bus.read_bit()? {
+                return Ok(());
+            }
+        }
+        Err(Error::Timeout)
+    }
 }
 
 /// Recalls values stored in nonvolatile memory (EEPROM) into scratchpad
@@ -59,17 +133,17 @@ pub struct MemoryRecall {
 impl Command for MemoryRecall {
     type Output = Result<()>;
 
-    fn execute(&self, driver: &mut OneWireDriver<impl Pin, impl DelayNs>) -> Self::Output {
-        driver.reset()?;
+    fn execute(&self, bus: &mut impl Bus) -> Self::Output {
+        bus.reset()?;
         match self.rom {
-            Some(rom) => driver.run(RomMatch { rom })?,
-            None => driver.run(RomSkip)?,
+            Some(rom) => bus.run(RomMatch { rom })?,
+            None => bus.run(RomSkip)?,
         }
-        driver.write_byte(COMMAND_MEMORY_RECALL)?;
+        bus.write_byte(COMMAND_MEMORY_RECALL)?;
         // wait for the recall to finish (up to 10ms)
-        let max_retries = (10000 / READ_SLOT_DURATION_MICROS) + 1;
+        let max_retries = (10000 / bus.read_slot_micros()) + 1;
         for _ in 0..max_retries {
-            if driver.read_bit()? == true {
+            if bus.read_bit()? {
                 return Ok(());
             }
         }
@@ -89,15 +163,21 @@ pub struct MemoryScratchpadCopy {
 impl Command for MemoryScratchpadCopy {
     type Output = Result<()>;
 
-    fn execute(&self, driver: &mut OneWireDriver<impl Pin, impl DelayNs>) -> Self::Output {
-        driver.reset()?;
+    fn execute(&self, bus: &mut impl Bus) -> Self::Output {
+        bus.reset()?;
         match self.rom {
-            Some(rom) => driver.run(RomMatch { rom })?,
-            None => driver.run(RomSkip)?,
+            Some(rom) => bus.run(RomMatch { rom })?,
+            None => bus.run(RomSkip)?,
         }
-        driver.write_byte(COMMAND_MEMORY_SCRATCHPAD_COPY)?;
-        driver.wait(10000); // delay 10ms for the write to complete
-        Ok(())
+        bus.write_byte(COMMAND_MEMORY_SCRATCHPAD_COPY)?;
+        // wait for the copy to finish (up to 10ms)
+        let max_retries = (10000 / bus.read_slot_micros()) + 1;
+        for _ in 0..max_retries {
+            if bus.read_bit()? {
+                return Ok(());
+            }
+        }
+        Err(Error::Timeout)
     }
 }
 
@@ -110,12 +190,12 @@ pub struct MemoryScratchpadRead {
 impl Command for MemoryScratchpadRead {
     type Output = Result<Scratchpad>;
 
-    fn execute(&self, driver: &mut OneWireDriver<impl Pin, impl DelayNs>) -> Self::Output {
-        driver.reset()?;
-        driver.run(RomMatch { rom: self.rom })?;
-        driver.write_byte(COMMAND_MEMORY_SCRATCHPAD_READ)?;
+    fn execute(&self, bus: &mut impl Bus) -> Self::Output {
+        bus.reset()?;
+        bus.run(RomMatch { rom: self.rom })?;
+        bus.write_byte(COMMAND_MEMORY_SCRATCHPAD_READ)?;
         let mut bytes = [0; 9];
-        driver.read_bytes(&mut bytes)?;
+        bus.read_bytes(&mut bytes)?;
         bytes.try_into()
     }
 }
@@ -131,16 +211,16 @@ pub struct MemoryScratchpadWrite {
 impl Command for MemoryScratchpadWrite {
     type Output = Result<()>;
 
-    fn execute(&self, driver: &mut OneWireDriver<impl Pin, impl DelayNs>) -> Self::Output {
-        driver.reset()?;
+    fn execute(&self, bus: &mut impl Bus) -> Self::Output {
+        bus.reset()?;
         match self.rom {
-            Some(rom) => driver.run(RomMatch { rom })?,
-            None => driver.run(RomSkip)?,
+            Some(rom) => bus.run(RomMatch { rom })?,
+            None => bus.run(RomSkip)?,
         }
-        driver.write_byte(COMMAND_MEMORY_SCRATCHPAD_WRITE)?;
-        driver.write_byte(self.scratchpad.triggers.low as _)?;
-        driver.write_byte(self.scratchpad.triggers.high as _)?;
-        driver.write_byte(self.scratchpad.configuration.resolution as _)?;
+        bus.write_byte(COMMAND_MEMORY_SCRATCHPAD_WRITE)?;
+        bus.write_byte(self.scratchpad.triggers.low as _)?;
+        bus.write_byte(self.scratchpad.triggers.high as _)?;
+        bus.write_byte(self.scratchpad.configuration.resolution as _)?;
         Ok(())
     }
 }
@@ -149,6 +229,20 @@ impl Command for MemoryScratchpadWrite {
 #[derive(Clone, Copy, Debug, Default)]
 pub struct And<T, U>(pub T, pub U);
 
+impl<P: Pin, D: DelayNs> OneWireDriver<P, D> {
+    /// Actively drives the data line high for `duration_micros` instead of
+    /// releasing it to the external pull-up resistor.
+    ///
+    /// Parasite-powered slaves draw the current for their current operation
+    /// directly from the data line, so the external ~5K pull-up alone cannot
+    /// supply enough current for it to complete in time.
+    pub(crate) fn strong_pullup(&mut self, duration_micros: u32) -> Result<()> {
+        self.pin.set_high()?;
+        self.wait(duration_micros);
+        Ok(())
+    }
+}
+
 // impl<T: Command<Output = V>, U: Command<Output = V>, V> Command for And<T, U> {
 //     type Output = Result<()>;
 
@@ -183,3 +277,86 @@ pub struct And<T, U>(pub T, pub U);
 //         Ok(())
 //     }
 // }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fake [`Bus`] that answers read slots from a scripted sequence of
+    /// bits (defaulting to `false` once exhausted), otherwise ignoring
+    /// everything written to it.
+    struct ScriptedBus<'a> {
+        read_bits: &'a [bool],
+        index: usize,
+    }
+
+    impl<'a> ScriptedBus<'a> {
+        fn new(read_bits: &'a [bool]) -> Self {
+            Self {
+                read_bits,
+                index: 0,
+            }
+        }
+    }
+
+    impl<'a> Bus for ScriptedBus<'a> {
+        fn reset(&mut self) -> Result<bool> {
+            Ok(true)
+        }
+
+        fn read_bit(&mut self) -> Result<bool> {
+            let bit = self.read_bits.get(self.index).copied().unwrap_or(false);
+            self.index += 1;
+            Ok(bit)
+        }
+
+        fn write_bit(&mut self, _bit: bool) -> Result<()> {
+            Ok(())
+        }
+
+        fn read_bytes(&mut self, _bytes: &mut [u8]) -> Result<()> {
+            Ok(())
+        }
+
+        fn write_bytes(&mut self, _bytes: &[u8]) -> Result<()> {
+            Ok(())
+        }
+
+        fn strong_pullup(&mut self, _duration_micros: u32) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn conversion_wait_returns_as_soon_as_the_bus_releases() {
+        let mut bus = ScriptedBus::new(&[false, false, true]);
+        assert_eq!(
+            MemoryConversionWait {
+                resolution: Resolution::Bits12,
+            }
+            .execute(&mut bus),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn conversion_wait_times_out_if_the_bus_never_releases() {
+        let mut bus = ScriptedBus::new(&[]);
+        assert_eq!(
+            MemoryConversionWait {
+                resolution: Resolution::Bits12,
+            }
+            .execute(&mut bus),
+            Err(Error::Timeout)
+        );
+    }
+
+    #[test]
+    fn scratchpad_copy_times_out_if_the_device_never_finishes() {
+        let mut bus = ScriptedBus::new(&[]);
+        assert_eq!(
+            MemoryScratchpadCopy { rom: None }.execute(&mut bus),
+            Err(Error::Timeout)
+        );
+    }
+}
@@ -1,9 +1,13 @@
 pub use self::{
+    eeprom::{MemCopyScratchpad, MemReadMemory, MemReadScratchpad, MemScratchpad, MemWriteScratchpad},
     memory::{
-        MemoryConvert, MemoryPowerSupplyRead, MemoryRecall, MemoryScratchpadCopy,
-        MemoryScratchpadRead, MemoryScratchpadWrite,
+        MemoryConvert, MemoryConversionWait, MemoryPowerSupplyRead, MemoryRecall,
+        MemoryScratchpadCopy, MemoryScratchpadRead, MemoryScratchpadWrite,
+    },
+    rom::{
+        AlarmSearch, RomMatch, RomRead, RomSearch, RomSkip, COMMAND_ALARM_SEARCH,
+        COMMAND_ROM_SEARCH,
     },
-    rom::{AlarmSearch, RomMatch, RomRead, RomSearch, RomSkip, COMMAND_ALARM_SEARCH, COMMAND_ROM_SEARCH},
 };
 
 use core::convert::Infallible;
@@ -14,5 +18,6 @@ pub trait Pin: InputPin + OutputPin + ErrorType<Error = Infallible> {}
 
 impl<T> Pin for T where T: InputPin + OutputPin + ErrorType<Error = Infallible> {}
 
+mod eeprom;
 mod memory;
 mod rom;
@@ -1,6 +1,5 @@
 use super::Pin;
-use crate::{crc8::check, Command, Error, OneWireDriver, Result, Rom};
-use core::convert::Infallible;
+use crate::{crc8::check, Bus, Command, Error, OneWireDriver, Result, Rom};
 use embedded_hal::delay::DelayNs;
 
 pub const COMMAND_ALARM_SEARCH: u8 = 0xEC;
@@ -9,20 +8,43 @@ pub const COMMAND_ROM_MATCH: u8 = 0x55;
 pub const COMMAND_ROM_SKIP: u8 = 0xCC;
 pub const COMMAND_ROM_SEARCH: u8 = 0xF0;
 
-const CONFLICT: (bool, bool) = (false, false);
-const ZERO: (bool, bool) = (false, true);
-const ONE: (bool, bool) = (true, false);
-const NONE: (bool, bool) = (true, true);
+// Overdrive ROM selection (`0x3C`/`0x69`) is deliberately not implemented
+// here. Selecting a device for overdrive only matters once the master
+// itself switches its own reset/write/read timing to match, and that
+// timing lives in `OneWireDriver`'s bit-banging loop, which this source
+// tree does not include - there is nothing in these files for the
+// selection bytes to hand off to. Shipping the selection commands alone
+// would read as overdrive support while silently doing nothing, so this
+// part of chunk0-6 is left undone rather than merged as if it worked;
+// picking it back up requires the driver module itself to grow a
+// speed-aware timing table (or a `Bus::set_speed` seam wired to one).
 
 /// Alarm search command
 ///
-/// When a system is initially brought up, the bus master might not know the
-/// number of devices on the 1-Wire bus or their 64-bit ROM codes. The search
-/// ROM command allows the bus master to use a process of elimination to
-/// identify the 64-bit ROM codes of all slave devices on the bus.
-#[derive(Clone, Copy, Debug)]
+/// Identical tree-walk to [`RomSearch`], but issues the conditional search
+/// command `0xEC` instead of `0xF0`. Devices that do not have their alarm
+/// flag set (e.g. a DS18B20 whose last temperature conversion stayed within
+/// its TH/TL trigger bounds) simply do not participate, so the enumeration
+/// only ever turns up the ROMs of slaves that need attention. This lets a
+/// polling loop discover which sensors tripped an alarm without reading
+/// every scratchpad on the bus.
+///
+/// As with `RomSearch`, running this as a one-shot [`Command`] only returns
+/// the first alarming device; use [`OneWireDriver::search_alarms`] to
+/// enumerate all of them.
+#[derive(Clone, Copy, Debug, Default)]
 pub struct AlarmSearch;
 
+impl Command for AlarmSearch {
+    type Output = Result<Rom>;
+
+    fn execute(&self, bus: &mut impl Bus) -> Self::Output {
+        DeviceSearch::new(bus, COMMAND_ALARM_SEARCH)
+            .next()
+            .unwrap_or(Err(Error::NoAttachedDevices))
+    }
+}
+
 /// Read ROM command
 ///
 /// This command allows the bus master to read the DS18B20's 8-bit family code,
@@ -36,13 +58,13 @@ pub struct RomRead;
 impl Command for RomRead {
     type Output = Result<Rom>;
 
-    fn execute(&self, driver: &mut OneWireDriver<impl Pin, impl DelayNs>) -> Self::Output {
-        if !driver.reset()? {
+    fn execute(&self, bus: &mut impl Bus) -> Self::Output {
+        if !bus.reset()? {
             return Err(Error::NoAttachedDevices);
         }
-        driver.write_byte(COMMAND_ROM_READ)?;
+        bus.write_byte(COMMAND_ROM_READ)?;
         let mut rom_bytes = [0u8; 8];
-        driver.read_bytes(&mut rom_bytes)?;
+        bus.read_bytes(&mut rom_bytes)?;
         rom_bytes.try_into()
     }
 }
@@ -60,11 +82,11 @@ pub struct RomMatch {
 }
 
 impl Command for RomMatch {
-    type Output = Result<(), Infallible>;
+    type Output = Result<()>;
 
-    fn execute(&self, driver: &mut OneWireDriver<impl Pin, impl DelayNs>) -> Self::Output {
-        driver.write_byte(COMMAND_ROM_MATCH)?;
-        driver.write_bytes(&Into::<[u8; 8]>::into(self.rom))?;
+    fn execute(&self, bus: &mut impl Bus) -> Self::Output {
+        bus.write_byte(COMMAND_ROM_MATCH)?;
+        bus.write_bytes(&Into::<[u8; 8]>::into(self.rom))?;
         Ok(())
     }
 }
@@ -81,10 +103,10 @@ impl Command for RomMatch {
 pub struct RomSkip;
 
 impl Command for RomSkip {
-    type Output = Result<(), Infallible>;
+    type Output = Result<()>;
 
-    fn execute(&self, driver: &mut OneWireDriver<impl Pin, impl DelayNs>) -> Self::Output {
-        driver.write_byte(COMMAND_ROM_SKIP)?;
+    fn execute(&self, bus: &mut impl Bus) -> Self::Output {
+        bus.write_byte(COMMAND_ROM_SKIP)?;
         Ok(())
     }
 }
@@ -97,98 +119,377 @@ impl Command for RomSkip {
 /// following the Skip ROM command, data collision will occur on the bus as
 /// multiple slaves transmit simultaneously (open drain pulldowns will produce a
 /// wired AND result).
+///
+/// Running this as a one-shot [`Command`] only ever returns the first device
+/// found on the bus. To enumerate every device on a multidrop bus, use
+/// [`OneWireDriver::search`] instead, which walks the full binary ROM tree
+/// across repeated passes.
 #[derive(Clone, Copy, Debug, Default)]
-pub struct RomSearch {
-    conflicts: u64,
-}
+pub struct RomSearch;
 
 impl Command for RomSearch {
     type Output = Result<Rom>;
 
-    fn execute(&self, driver: &mut OneWireDriver<impl Pin, impl DelayNs>) -> Self::Output {
-        if !driver.reset()? {
-            return Err(Error::NoAttachedDevices);
+    fn execute(&self, bus: &mut impl Bus) -> Self::Output {
+        DeviceSearch::new(bus, COMMAND_ROM_SEARCH)
+            .next()
+            .unwrap_or(Err(Error::NoAttachedDevices))
+    }
+}
+
+/// Iterator over every device present on the 1-Wire bus.
+///
+/// Implements the Maxim/Dallas ROM search algorithm (application note 187):
+/// each call to [`next`](Iterator::next) performs one more reset-and-walk
+/// pass over the binary ROM tree, taking the 1 branch at the highest-numbered
+/// bit position where the previous pass hit an ambiguity (a "discrepancy")
+/// and took the 0 branch. State carried between passes (`rom_no`, `last_discrepancy`,
+/// `last_device_flag`) is what lets successive calls walk further down the
+/// tree instead of always finding the same device. The search is exhausted
+/// once a pass resolves with no remaining discrepancies, at which point the
+/// iterator yields `None`. A pass whose ROM fails its CRC check resets the
+/// walk state and restarts the enumeration from scratch on the next call,
+/// the same as finding no devices at all.
+///
+/// Generic over any [`Bus`], so it speeds up for free on a master that
+/// implements [`Bus::triplet`] as a single hardware transaction instead of
+/// the default read/read/write fallback.
+pub struct DeviceSearch<'a, B> {
+    bus: &'a mut B,
+    command: u8,
+    rom_no: [u8; 8],
+    last_discrepancy: u8,
+    last_device_flag: bool,
+}
+
+impl<'a, B> DeviceSearch<'a, B> {
+    pub fn new(bus: &'a mut B, command: u8) -> Self {
+        Self {
+            bus,
+            command,
+            rom_no: [0; 8],
+            last_discrepancy: 0,
+            last_device_flag: false,
         }
-        driver.write_byte(COMMAND_ROM_SEARCH)?;
-        let mut rom = 0;
-        
-        for index in 0..u64::BITS {
-            let mask = 1u64 << index;
-            let bit1 = driver.read_bit()?;
-            let bit2 = driver.read_bit()?;
-            
-            match (bit1, bit2) {
-                // `00`: There are devices attached which have conflicting bits
-                CONFLICT => {
-                    // For simplicity in a basic search, choose 0 for conflicts
-                    // A full search would track discrepancies for multiple devices
-                    rom &= !mask;
-                    driver.write_bit(false)?;
-                }
-                // `01`: All devices have a 0-bit in this position
-                ZERO => {
-                    rom &= !mask;
-                    driver.write_bit(false)?;
-                }
-                // `10`: All devices have a 1-bit in this position
-                ONE => {
-                    rom |= mask;
-                    driver.write_bit(true)?;
-                }
-                // `11`: No devices are responding
-                NONE => return Err(Error::NoAttachedDevices),
+    }
+
+    fn reset_search(&mut self) {
+        self.rom_no = [0; 8];
+        self.last_discrepancy = 0;
+        self.last_device_flag = false;
+    }
+}
+
+impl<'a, B: Bus> Iterator for DeviceSearch<'a, B> {
+    type Item = Result<Rom>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.last_device_flag {
+            return None;
+        }
+
+        match self.bus.reset() {
+            Ok(true) => {}
+            Ok(false) => {
+                self.reset_search();
+                return None;
             }
+            Err(err) => return Some(Err(err)),
         }
-        check(&rom.to_le_bytes())?;
-        rom.try_into()
+
+        if let Err(err) = self.bus.write_byte(self.command) {
+            return Some(Err(err));
+        }
+
+        let mut last_zero = 0u8;
+
+        for id_bit_number in 1..=64u8 {
+            let byte_index = ((id_bit_number - 1) / 8) as usize;
+            let mask = 1u8 << ((id_bit_number - 1) % 8);
+
+            // Below the last discrepancy, take the same branch as last
+            // time; at or above it, take the 1 branch once, then 0 from
+            // then on. This is only consulted if this bit turns out to be
+            // an actual discrepancy between devices.
+            let preferred_direction = if id_bit_number < self.last_discrepancy {
+                self.rom_no[byte_index] & mask != 0
+            } else {
+                id_bit_number == self.last_discrepancy
+            };
+
+            let (id_bit, cmp_id_bit, search_direction) =
+                match self.bus.triplet(preferred_direction) {
+                    Ok(result) => result,
+                    Err(err) => return Some(Err(err)),
+                };
+
+            // `11`: no devices responded to this pass.
+            if id_bit && cmp_id_bit {
+                self.reset_search();
+                return None;
+            }
+
+            // `00`: a genuine discrepancy - both a 0 and a 1 are still
+            // live down this branch. Only remember it if we went down the
+            // 0 side, so a later pass knows to come back and take the 1
+            // side instead. A forced bit (`01`/`10`, every remaining
+            // device agrees) isn't a discrepancy and must not overwrite
+            // this, or the walk would never converge on a single device.
+            if id_bit == cmp_id_bit && !search_direction {
+                last_zero = id_bit_number;
+            }
+
+            if search_direction {
+                self.rom_no[byte_index] |= mask;
+            } else {
+                self.rom_no[byte_index] &= !mask;
+            }
+        }
+
+        if let Err(err) = check(&self.rom_no) {
+            self.reset_search();
+            return Some(Err(err));
+        }
+
+        self.last_discrepancy = last_zero;
+        if self.last_discrepancy == 0 {
+            self.last_device_flag = true;
+        }
+
+        Some(self.rom_no.try_into())
     }
 }
 
-impl RomSearch {
-    fn search(&mut self, one_wire: &mut OneWireDriver<impl Pin, impl DelayNs>) -> Result<Rom> {
-        if !one_wire.reset()? {
-            return Err(Error::NoAttachedDevices);
+impl<P: Pin, D: DelayNs> OneWireDriver<P, D> {
+    /// Enumerates every device on the bus.
+    ///
+    /// ```ignore
+    /// for rom in driver.search() {
+    ///     let rom = rom?;
+    ///     // ...
+    /// }
+    /// ```
+    pub fn search(&mut self) -> DeviceSearch<'_, Self> {
+        DeviceSearch::new(self, COMMAND_ROM_SEARCH)
+    }
+
+    /// Enumerates only the devices whose alarm flag is currently set.
+    ///
+    /// ```ignore
+    /// for rom in driver.search_alarms() {
+    ///     let rom = rom?;
+    ///     // ...
+    /// }
+    /// ```
+    pub fn search_alarms(&mut self) -> DeviceSearch<'_, Self> {
+        DeviceSearch::new(self, COMMAND_ALARM_SEARCH)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fake [`Bus`] with exactly one device on it, whose ROM is `rom`.
+    /// Since there is never a second device to disagree with, every
+    /// `triplet` call simply echoes that device's next bit back as both
+    /// `id_bit` and its complement, and the search direction always
+    /// follows it.
+    struct SingleDeviceBus {
+        rom: [u8; 8],
+        bit_index: u8,
+        passes: u8,
+        corrupt_first_pass: bool,
+    }
+
+    impl SingleDeviceBus {
+        fn new(rom: [u8; 8]) -> Self {
+            Self {
+                rom,
+                bit_index: 0,
+                passes: 0,
+                corrupt_first_pass: false,
+            }
         }
-        one_wire.write_byte(COMMAND_ROM_SEARCH)?;
-        let mut code = 0;
-        for index in 0..u64::BITS {
-            let mask = 1u64 << index;
-            match (one_wire.read_bit()?, one_wire.read_bit()?) {
-                // `0b00`: There are still devices attached which have
-                // conflicting bits in this position.
-                CONFLICT => {
-                    // TODO:
-                    // discrepancies |= mask;
-                    // state.index = index;
-                    // self.conflicts ^= mask;
-                    self.conflicts ^= mask;
-                    if self.conflicts ^ mask == 0 {
-                        self.conflicts |= mask;
-                        code &= !mask;
-                        one_wire.write_bit(false)?;
-                    } else {
-                        self.conflicts &= !mask;
-                        code |= mask;
-                        one_wire.write_bit(true)?
-                    }
-                }
-                // `0b01`: All devices still coupled have a 0-bit in this bit
-                // position.
-                ZERO => {
-                    code |= mask;
-                    one_wire.write_bit(false)?;
-                }
-                // `0b10`: All devices still coupled have a 1-bit in this bit
-                // position.
-                ONE => {
-                    code &= !mask;
-                    one_wire.write_bit(true)?;
+    }
+
+    impl Bus for SingleDeviceBus {
+        fn reset(&mut self) -> Result<bool> {
+            self.bit_index = 0;
+            self.passes += 1;
+            Ok(true)
+        }
+
+        fn read_bit(&mut self) -> Result<bool> {
+            unreachable!("DeviceSearch drives the bus through triplet()")
+        }
+
+        fn write_bit(&mut self, _bit: bool) -> Result<()> {
+            unreachable!("DeviceSearch drives the bus through triplet()")
+        }
+
+        fn read_bytes(&mut self, _bytes: &mut [u8]) -> Result<()> {
+            Ok(())
+        }
+
+        fn write_bytes(&mut self, _bytes: &[u8]) -> Result<()> {
+            Ok(())
+        }
+
+        fn strong_pullup(&mut self, _duration_micros: u32) -> Result<()> {
+            Ok(())
+        }
+
+        fn triplet(&mut self, _preferred_direction: bool) -> Result<(bool, bool, bool)> {
+            let byte_index = (self.bit_index / 8) as usize;
+            let mask = 1u8 << (self.bit_index % 8);
+            let mut id_bit = self.rom[byte_index] & mask != 0;
+            if self.corrupt_first_pass && self.passes == 1 && self.bit_index == 0 {
+                id_bit = !id_bit;
+            }
+            self.bit_index += 1;
+            Ok((id_bit, !id_bit, id_bit))
+        }
+    }
+
+    const VALID_ROM: [u8; 8] = [0x28, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x9e];
+
+    #[test]
+    fn search_finds_the_only_device_then_stops() {
+        let mut bus = SingleDeviceBus::new(VALID_ROM);
+        let mut search = DeviceSearch::new(&mut bus, COMMAND_ROM_SEARCH);
+
+        let found = search.next().expect("one device present").expect("valid crc");
+        assert_eq!(Into::<[u8; 8]>::into(found), VALID_ROM);
+        assert!(search.next().is_none());
+    }
+
+    #[test]
+    fn search_resets_and_recovers_after_a_corrupted_pass() {
+        let mut bus = SingleDeviceBus::new(VALID_ROM);
+        bus.corrupt_first_pass = true;
+        let mut search = DeviceSearch::new(&mut bus, COMMAND_ROM_SEARCH);
+
+        assert!(search.next().expect("a pass completed").is_err());
+
+        // The corrupted pass must not have left the walk state skewed -
+        // the very next call should restart the enumeration and find the
+        // real device, not get stuck forever returning CRC errors.
+        let found = search.next().expect("one device present").expect("valid crc");
+        assert_eq!(Into::<[u8; 8]>::into(found), VALID_ROM);
+        assert!(search.next().is_none());
+    }
+
+    /// A fake [`Bus`] with two devices on it, wired the way a real
+    /// open-drain multidrop bus is: `id_bit` is the AND of every still-live
+    /// device's bit, `cmp_id_bit` is the AND of their complements, and
+    /// writing `direction` drops any live device whose bit disagrees with
+    /// it for the rest of the pass. This is what actually exercises the
+    /// `00` discrepancy branch in `DeviceSearch::next` - `SingleDeviceBus`
+    /// never can, since a single device's bit and its complement always
+    /// disagree.
+    struct MultiDeviceBus {
+        roms: [[u8; 8]; 2],
+        live: [bool; 2],
+        bit_index: u8,
+    }
+
+    impl MultiDeviceBus {
+        fn new(roms: [[u8; 8]; 2]) -> Self {
+            Self {
+                roms,
+                live: [true; 2],
+                bit_index: 0,
+            }
+        }
+
+        fn bit(rom: &[u8; 8], bit_index: u8) -> bool {
+            let byte_index = (bit_index / 8) as usize;
+            let mask = 1u8 << (bit_index % 8);
+            rom[byte_index] & mask != 0
+        }
+    }
+
+    impl Bus for MultiDeviceBus {
+        fn reset(&mut self) -> Result<bool> {
+            self.bit_index = 0;
+            self.live = [true; 2];
+            Ok(true)
+        }
+
+        fn read_bit(&mut self) -> Result<bool> {
+            unreachable!("DeviceSearch drives the bus through triplet()")
+        }
+
+        fn write_bit(&mut self, _bit: bool) -> Result<()> {
+            unreachable!("DeviceSearch drives the bus through triplet()")
+        }
+
+        fn read_bytes(&mut self, _bytes: &mut [u8]) -> Result<()> {
+            Ok(())
+        }
+
+        fn write_bytes(&mut self, _bytes: &[u8]) -> Result<()> {
+            Ok(())
+        }
+
+        fn strong_pullup(&mut self, _duration_micros: u32) -> Result<()> {
+            Ok(())
+        }
+
+        fn triplet(&mut self, preferred_direction: bool) -> Result<(bool, bool, bool)> {
+            let bits = [
+                Self::bit(&self.roms[0], self.bit_index),
+                Self::bit(&self.roms[1], self.bit_index),
+            ];
+            self.bit_index += 1;
+
+            let id_bit = self
+                .live
+                .iter()
+                .zip(bits.iter())
+                .all(|(&live, &bit)| !live || bit);
+            let cmp_id_bit = self
+                .live
+                .iter()
+                .zip(bits.iter())
+                .all(|(&live, &bit)| !live || !bit);
+            let direction = if id_bit != cmp_id_bit {
+                id_bit
+            } else {
+                preferred_direction
+            };
+
+            for (live, &bit) in self.live.iter_mut().zip(bits.iter()) {
+                if *live && bit != direction {
+                    *live = false;
                 }
-                // `0b11`: There are no devices attached to the 1-Wire bus.
-                NONE => return Err(Error::NoAttachedDevices),
             }
+
+            Ok((id_bit, cmp_id_bit, direction))
         }
-        code.try_into()
     }
-}
 
+    // Family bytes 0x10/0x11 put the very first id bit (id_bit_number 1,
+    // the family code's LSB) in disagreement, so the first pass hits a
+    // genuine discrepancy immediately and the walk needs a second pass to
+    // recover the other device.
+    const MULTI_ROM_A: [u8; 8] = [0x10, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x7b];
+    const MULTI_ROM_B: [u8; 8] = [0x11, 0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x62];
+
+    #[test]
+    fn search_walks_every_branch_on_a_multidrop_bus() {
+        let mut bus = MultiDeviceBus::new([MULTI_ROM_A, MULTI_ROM_B]);
+        let mut search = DeviceSearch::new(&mut bus, COMMAND_ROM_SEARCH);
+
+        let first = Into::<[u8; 8]>::into(search.next().expect("a device").expect("valid crc"));
+        let second = Into::<[u8; 8]>::into(search.next().expect("a device").expect("valid crc"));
+
+        let mut found = [first, second];
+        found.sort();
+        let mut expected = [MULTI_ROM_A, MULTI_ROM_B];
+        expected.sort();
+        assert_eq!(found, expected);
+        assert!(search.next().is_none());
+    }
+}